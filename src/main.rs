@@ -1,13 +1,62 @@
 use clap::{crate_authors, Parser};
 use csv::{ReaderBuilder, StringRecord};
-use rand::Rng;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde_json::{Map, Number, Value};
 use std::{
+    cell::RefCell,
     cmp::Ordering,
     collections::BinaryHeap,
-    io::{Error, ErrorKind},
-    path::PathBuf,
+    io::{BufRead, BufReader, Error, ErrorKind, Seek, Write},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
 };
 
+/// Number of records the reader thread batches together before handing a
+/// chunk to the sampling thread.
+const READ_CHUNK_SIZE: usize = 1000;
+/// Number of chunks allowed to sit in the channel at once, bounding memory
+/// use while still letting the reader run ahead of the sampler.
+const CHANNEL_DEPTH: usize = 4;
+/// Indexed random access only pays off when the sample is a small slice of
+/// the file - below this ratio we fall back to the streaming reservoir.
+const INDEX_SAMPLE_RATIO: usize = 4;
+
+/// The shape of the input/output records. `Tsv`/`Csv` are both delimited
+/// text, differing only via `--delimiter`; `Jsonl` reads and writes one
+/// JSON object per line, keyed by header name instead of column position.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+#[clap(rename_all = "lower")]
+enum Format {
+    Tsv,
+    Csv,
+    Jsonl,
+}
+
+/// The delimiter a format implies when `--delimiter`/`--output-delimiter`
+/// isn't given explicitly. `Jsonl` ignores delimiters entirely, so its
+/// value here is never read.
+fn default_delimiter(format: Format) -> u8 {
+    match format {
+        Format::Tsv | Format::Jsonl => b'\t',
+        Format::Csv => b',',
+    }
+}
+
+/// Narrows a `--delimiter`/`--output-delimiter` value to the single ASCII
+/// byte the `csv` crate needs, rejecting non-ASCII characters instead of
+/// silently truncating them to their low byte (e.g. `€` U+20AC -> 0xAC).
+fn ascii_delimiter(c: char) -> Result<u8, Error> {
+    if c.is_ascii() {
+        Ok(c as u8)
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Delimiter '{}' is not a single ASCII character", c),
+        ))
+    }
+}
+
 #[derive(clap::Parser, Debug)]
 #[clap(
     author=crate_authors!(),
@@ -32,6 +81,55 @@ struct Cli {
     /// id column -  default is the first one
     #[clap(long, help = "Id column - default is the first one")]
     id_col: Option<String>,
+    /// seed for the random number generator, for reproducible sampling
+    #[clap(long, help = "Seed for the random number generator, for reproducible sampling")]
+    seed: Option<u64>,
+    /// delimiter used to parse the input file - defaults to tab, or comma
+    /// when --format is csv
+    #[clap(
+        short,
+        long,
+        help = "Delimiter used to parse the input file - defaults to tab, or comma for --format csv"
+    )]
+    delimiter: Option<char>,
+    /// delimiter used to write the output - defaults to --delimiter if set,
+    /// otherwise to the output format's own default (tab, or comma for csv)
+    #[clap(
+        long,
+        help = "Delimiter used to write the output - defaults to --delimiter, or the output format's own default"
+    )]
+    output_delimiter: Option<char>,
+    /// sidecar index of record byte offsets, built on first use and reused
+    /// afterwards - enables random-access sampling for unweighted runs
+    #[clap(
+        long,
+        help = "Sidecar index of record byte offsets, built on first use and reused afterwards"
+    )]
+    index: Option<PathBuf>,
+    /// input/output format - applies to both unless --output-format is set
+    #[clap(
+        long,
+        value_enum,
+        default_value = "tsv",
+        help = "Input/output format: tsv, csv, or jsonl"
+    )]
+    format: Format,
+    /// output format override - defaults to --format
+    #[clap(
+        long,
+        value_enum,
+        help = "Output format override - defaults to --format"
+    )]
+    output_format: Option<Format>,
+}
+
+thread_local! {
+    /// RNG used by `Line::cmp` to order two lines whose `position_index`
+    /// values cannot be compared (a NaN key); equal keys compare as equal.
+    ///
+    /// Seeded once at the start of `process_data` so tie-breaking is
+    /// reproducible alongside the main sampling RNG.
+    static TIE_RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
 }
 
 #[derive(Debug, PartialEq)]
@@ -47,6 +145,89 @@ struct DataProc {
     args: Cli,
 }
 
+/// Iterates the record chunks coming off the reader thread's channel,
+/// handing their buffers back over `recycle_tx` once drained so the reader
+/// can reuse the allocation for its next chunk instead of growing a fresh
+/// `Vec` per batch.
+struct RecycledChunks {
+    rx: mpsc::Receiver<Vec<Result<StringRecord, Error>>>,
+    recycle_tx: mpsc::Sender<Vec<Result<StringRecord, Error>>>,
+    current: Vec<Result<StringRecord, Error>>,
+    cursor: usize,
+}
+
+impl RecycledChunks {
+    fn new(
+        rx: mpsc::Receiver<Vec<Result<StringRecord, Error>>>,
+        recycle_tx: mpsc::Sender<Vec<Result<StringRecord, Error>>>,
+    ) -> Self {
+        Self {
+            rx,
+            recycle_tx,
+            current: Vec::new(),
+            cursor: 0,
+        }
+    }
+}
+
+impl Iterator for RecycledChunks {
+    type Item = Result<StringRecord, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.cursor < self.current.len() {
+                let item = std::mem::replace(&mut self.current[self.cursor], Ok(StringRecord::new()));
+                self.cursor += 1;
+                return Some(item);
+            }
+
+            // The current chunk is drained; hand its allocation back to the
+            // reader thread before blocking on the next one.
+            if self.current.capacity() > 0 {
+                self.current.clear();
+                let _ = self.recycle_tx.send(std::mem::take(&mut self.current));
+            }
+            self.cursor = 0;
+            self.current = self.rx.recv().ok()?;
+        }
+    }
+}
+
+/// Where sampled rows go. `Delimited` wraps the existing CSV/TSV writer;
+/// `Jsonl` writes one JSON object per line, built from `headers` paired
+/// with each record's fields, and has no header row of its own.
+enum OutputSink {
+    Delimited(Box<csv::Writer<std::io::Stdout>>),
+    Jsonl(std::io::Stdout),
+}
+
+impl OutputSink {
+    fn write_header(&mut self, headers: &StringRecord) -> Result<(), Error> {
+        match self {
+            OutputSink::Delimited(w) => w
+                .write_record(headers)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e)),
+            OutputSink::Jsonl(_) => Ok(()),
+        }
+    }
+
+    fn write_record(&mut self, headers: &StringRecord, record: &StringRecord) -> Result<(), Error> {
+        match self {
+            OutputSink::Delimited(w) => w
+                .write_record(record)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e)),
+            OutputSink::Jsonl(stdout) => writeln!(stdout, "{}", record_to_json(headers, record)),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        match self {
+            OutputSink::Delimited(w) => w.flush(),
+            OutputSink::Jsonl(stdout) => stdout.flush(),
+        }
+    }
+}
+
 impl Eq for Line {}
 
 impl Ord for Line {
@@ -56,12 +237,13 @@ impl Ord for Line {
             None => {
                 log::warn!("Warning: Unprecedented precision challenge detected in float indexes. Resolving tie randomly.");
 
-                let mut rng = rand::thread_rng();
-                if rng.gen::<f64>() > 0.5 {
-                    other.tie_breaker.cmp(&self.tie_breaker)
-                } else {
-                    self.tie_breaker.cmp(&other.tie_breaker)
-                }
+                TIE_RNG.with(|rng| {
+                    if rng.borrow_mut().gen::<f64>() > 0.5 {
+                        other.tie_breaker.cmp(&self.tie_breaker)
+                    } else {
+                        self.tie_breaker.cmp(&other.tie_breaker)
+                    }
+                })
             }
         }
     }
@@ -87,9 +269,9 @@ impl DataProc {
         log::debug!("{:?}", self.args);
     }
 
-    fn setup_writer(&self) -> csv::Writer<std::io::Stdout> {
+    fn setup_writer(&self, delim: u8) -> csv::Writer<std::io::Stdout> {
         csv::WriterBuilder::new()
-            .delimiter(b'\t')
+            .delimiter(delim)
             .from_writer(std::io::stdout())
     }
 
@@ -100,22 +282,168 @@ impl DataProc {
             .expect("Failed to read the file")
     }
 
+    /// Derives the synthesized header set for a JSON Lines input from the
+    /// keys of its first record. Relies on `serde_json`'s `preserve_order`
+    /// feature so the synthesized column order matches the object's field
+    /// order (e.g. for the default `--id-col`), the same way it matches
+    /// source column order for CSV/TSV. This is a hard schema assumption:
+    /// every later row must be a subset of the first row's keys, or reading
+    /// fails with an error rather than silently dropping the extra column.
+    fn jsonl_headers(&self) -> Result<StringRecord, Error> {
+        let file = std::fs::File::open(&self.args.file)?;
+        let first_line = BufReader::new(file)
+            .lines()
+            .find_map(|line| match line {
+                Ok(line) if line.trim().is_empty() => None,
+                other => Some(other),
+            })
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "Empty JSON Lines input"))??;
+
+        let value: Value = serde_json::from_str(&first_line)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        let object = value
+            .as_object()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Each JSON Lines row must be an object"))?;
+
+        Ok(object.keys().cloned().collect())
+    }
+
+    /// Fingerprints the input file (canonical path, size, nanosecond-precision
+    /// mtime, delimiter) so a sidecar index can be recognized as stale once
+    /// the input changes underneath it, or the sidecar is reused against a
+    /// different input file. Nanosecond precision catches sub-second edits
+    /// that a whole-second mtime would miss; the canonical path catches a
+    /// same-size/mtime swap to a different file under the same `--index`.
+    fn index_fingerprint(&self, delim: u8) -> Result<String, Error> {
+        let metadata = std::fs::metadata(&self.args.file)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(Error::other)?;
+        let canonical_path = std::fs::canonicalize(&self.args.file)?;
+        Ok(format!(
+            "{}:{}:{}.{:09}:{}",
+            canonical_path.display(),
+            metadata.len(),
+            modified.as_secs(),
+            modified.subsec_nanos(),
+            delim
+        ))
+    }
+
+    /// Loads a previously built byte-offset index, or builds and persists
+    /// one by scanning the input file once. The index is rebuilt if it's
+    /// missing, stale (the input file changed since it was built), or
+    /// corrupt, rather than trusting or panicking on bad sidecar content.
+    fn load_or_build_index(&self, index_path: &Path, delim: u8) -> Result<Vec<u64>, Error> {
+        let fingerprint = self.index_fingerprint(delim)?;
+
+        if index_path.exists() {
+            let content = std::fs::read_to_string(index_path).unwrap_or_default();
+            let mut lines = content.lines();
+            if lines.next() == Some(fingerprint.as_str()) {
+                let parsed: Result<Vec<u64>, _> = lines
+                    .filter(|line| !line.is_empty())
+                    .map(|line| line.parse::<u64>())
+                    .collect();
+                if let Ok(offsets) = parsed {
+                    return Ok(offsets);
+                }
+            }
+            log::warn!(
+                "Index at {:?} is stale or corrupt; rebuilding it",
+                index_path
+            );
+        }
+
+        let mut reader = self.setup_reader(delim);
+        let mut offsets = Vec::new();
+        let mut record = StringRecord::new();
+        while reader.read_record(&mut record)? {
+            let position = record.position().expect("csv position tracking is on");
+            offsets.push(position.byte());
+        }
+
+        let content = std::iter::once(fingerprint)
+            .chain(offsets.iter().map(u64::to_string))
+            .collect::<Vec<_>>()
+            .join("\n");
+        std::fs::write(index_path, content)?;
+
+        Ok(offsets)
+    }
+
+    /// Seeks directly to `sample_count` randomly chosen row offsets instead
+    /// of streaming the whole file through the reservoir heap.
+    fn sample_via_index(
+        &self,
+        delim: u8,
+        offsets: &[u64],
+        rng: &mut StdRng,
+    ) -> Result<Vec<StringRecord>, Error> {
+        let mut chosen = std::collections::BTreeSet::new();
+        while chosen.len() < self.args.sample_count.min(offsets.len()) {
+            chosen.insert(rng.gen_range(0..offsets.len()));
+        }
+
+        let mut file = std::fs::File::open(&self.args.file)?;
+        let mut records = Vec::with_capacity(chosen.len());
+        for index in chosen {
+            file.seek(std::io::SeekFrom::Start(offsets[index]))?;
+            let mut row_reader = ReaderBuilder::new()
+                .delimiter(delim)
+                .has_headers(false)
+                .from_reader(&file);
+            let mut record = StringRecord::new();
+            row_reader.read_record(&mut record)?;
+            records.push(record);
+        }
+
+        Ok(records)
+    }
+
     fn process_data(&self) -> Result<(), Error> {
-        let mut rng = rand::thread_rng();
-        let mut wtr = self.setup_writer();
-        let mut reader = self.setup_reader(b'\t');
-
-        let weight_col =
-            match &self.args.weights {
-                Some(w) => Some(reader.headers()?.iter().position(|r| r == w).ok_or_else(
-                    || Error::new(ErrorKind::NotFound, format!("Column '{}' not found.", w)),
-                )?),
-                None => None,
+        let mut rng = match self.args.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        TIE_RNG.with(|tie_rng| {
+            *tie_rng.borrow_mut() = match self.args.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
             };
+        });
+        let input_format = self.args.format;
+        let output_format = self.args.output_format.unwrap_or(input_format);
+        let delimiter = match self.args.delimiter {
+            Some(c) => ascii_delimiter(c)?,
+            None => default_delimiter(input_format),
+        };
+        let output_delimiter = match self.args.output_delimiter.or(self.args.delimiter) {
+            Some(c) => ascii_delimiter(c)?,
+            None => default_delimiter(output_format),
+        };
+
+        let headers = match input_format {
+            Format::Jsonl => self.jsonl_headers()?,
+            Format::Tsv | Format::Csv => self.setup_reader(delimiter).headers()?.clone(),
+        };
+        let mut wtr = match output_format {
+            Format::Jsonl => OutputSink::Jsonl(std::io::stdout()),
+            Format::Tsv | Format::Csv => {
+                OutputSink::Delimited(Box::new(self.setup_writer(output_delimiter)))
+            }
+        };
+
+        let weight_col = match &self.args.weights {
+            Some(w) => Some(headers.iter().position(|r| r == w).ok_or_else(|| {
+                Error::new(ErrorKind::NotFound, format!("Column '{}' not found.", w))
+            })?),
+            None => None,
+        };
 
         let id_col = match &self.args.id_col {
-            Some(w) => reader
-                .headers()?
+            Some(w) => headers
                 .iter()
                 .position(|r| r == w)
                 .expect("Id column not found"),
@@ -132,48 +460,136 @@ impl DataProc {
             None => vec![],
         };
 
-        reader = ReaderBuilder::new()
-            .delimiter(b'\t')
-            .from_path(&self.args.file)
-            .expect("Failed to read the input file");
-
-        let mut heap = BinaryHeap::new();
-        let mut i = 0;
-        for record in reader.records() {
-            let record = record?;
-            if !exclude.contains(&String::from(record.get(id_col).unwrap())) {
-                let weight: f64 = get_weight(weight_col, &record);
-                let rng = rng.gen::<f64>();
-                let index = if include.contains(&String::from(record.get(id_col).unwrap())) {
-                    f64::INFINITY
-                } else {
-                    (1.0 / weight) * rng.log2()
-                };
-                if index == 0.0 {
-                    panic!("Non-zero weights required for numerical precision.");
-                }
-                let line = Line {
-                    record: record.clone(),
-                    weight,
-                    randomness: rng,
-                    position_index: index,
-                    tie_breaker: i,
-                };
-                log::trace!("pushing line {:?}", &line);
-                heap.push(line);
-                i += 1;
-                if heap.len() > self.args.sample_count {
-                    let smallest = heap.pop();
-                    if let Some(poor_soul) = smallest {
-                        log::trace!("removing line  {:?}", poor_soul)
+        // Unweighted runs over a small slice of a huge delimited file skip
+        // the reservoir entirely and seek straight to the rows we want.
+        // The byte-offset index is CSV/TSV-specific, so JSON Lines input
+        // always falls back to the streaming path below.
+        if let Some(index_path) = &self.args.index {
+            if input_format != Format::Jsonl
+                && weight_col.is_none()
+                && include.is_empty()
+                && exclude.is_empty()
+            {
+                let offsets = self.load_or_build_index(index_path, delimiter)?;
+                if self.args.sample_count.saturating_mul(INDEX_SAMPLE_RATIO) <= offsets.len() {
+                    let records = self.sample_via_index(delimiter, &offsets, &mut rng)?;
+                    wtr.write_header(&headers)?;
+                    for record in &records {
+                        wtr.write_record(&headers, record)?;
                     }
+                    wtr.flush()?;
+                    return Ok(());
                 }
             }
         }
 
-        wtr.write_record(reader.headers()?)?;
+        let file = self.args.file.clone();
+        let (tx, rx) = mpsc::sync_channel::<Vec<Result<StringRecord, Error>>>(CHANNEL_DEPTH);
+        // Drained chunk buffers come back here so the reader thread can
+        // reuse their allocation instead of allocating a fresh Vec per chunk.
+        let (recycle_tx, recycle_rx) = mpsc::channel::<Vec<Result<StringRecord, Error>>>();
+        let reader_headers = headers.clone();
+        let reader_thread = thread::spawn(move || {
+            let mut chunk: Vec<Result<StringRecord, Error>> = Vec::with_capacity(READ_CHUNK_SIZE);
+            let next_chunk = |recycle_rx: &mpsc::Receiver<Vec<Result<StringRecord, Error>>>| {
+                recycle_rx
+                    .try_recv()
+                    .unwrap_or_else(|_| Vec::with_capacity(READ_CHUNK_SIZE))
+            };
+
+            match input_format {
+                Format::Jsonl => {
+                    let jsonl_file = match std::fs::File::open(&file) {
+                        Ok(file) => file,
+                        Err(e) => {
+                            let _ = tx.send(vec![Err(e)]);
+                            return;
+                        }
+                    };
+                    for line in BufReader::new(jsonl_file).lines() {
+                        let parsed = line.and_then(|line| {
+                            if line.trim().is_empty() {
+                                return Ok(None);
+                            }
+                            let value: Value = serde_json::from_str(&line)
+                                .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+                            let object = value.as_object().ok_or_else(|| {
+                                Error::new(ErrorKind::InvalidData, "Each JSON Lines row must be an object")
+                            })?;
+                            // The header set is synthesized from the first row, so a
+                            // later row with a key outside it would silently lose that
+                            // column; report it instead.
+                            if let Some(key) = object
+                                .keys()
+                                .find(|key| !reader_headers.iter().any(|header| header == key.as_str()))
+                            {
+                                return Err(Error::new(
+                                    ErrorKind::InvalidData,
+                                    format!(
+                                        "JSON Lines row has key '{}' not in the header set synthesized from the first row",
+                                        key
+                                    ),
+                                ));
+                            }
+                            Ok(Some(json_to_record(&reader_headers, &value)))
+                        });
+                        match parsed {
+                            Ok(Some(record)) => chunk.push(Ok(record)),
+                            Ok(None) => continue,
+                            Err(e) => chunk.push(Err(e)),
+                        }
+                        if chunk.len() == READ_CHUNK_SIZE {
+                            if tx
+                                .send(std::mem::replace(&mut chunk, next_chunk(&recycle_rx)))
+                                .is_err()
+                            {
+                                return;
+                            }
+                        }
+                    }
+                }
+                Format::Tsv | Format::Csv => {
+                    let mut reader = match ReaderBuilder::new().delimiter(delimiter).from_path(&file) {
+                        Ok(reader) => reader,
+                        Err(e) => {
+                            let _ = tx.send(vec![Err(Error::new(ErrorKind::InvalidData, e))]);
+                            return;
+                        }
+                    };
+                    for record in reader.records() {
+                        chunk.push(record.map_err(|e| Error::new(ErrorKind::InvalidData, e)));
+                        if chunk.len() == READ_CHUNK_SIZE {
+                            if tx
+                                .send(std::mem::replace(&mut chunk, next_chunk(&recycle_rx)))
+                                .is_err()
+                            {
+                                return;
+                            }
+                        }
+                    }
+                }
+            }
+
+            if !chunk.is_empty() {
+                let _ = tx.send(chunk);
+            }
+        });
+
+        let records = RecycledChunks::new(rx, recycle_tx);
+        let heap = sample_reservoir(
+            records,
+            self.args.sample_count,
+            weight_col,
+            id_col,
+            &include,
+            &exclude,
+            &mut rng,
+        )?;
+        reader_thread.join().expect("Reader thread panicked");
+
+        wtr.write_header(&headers)?;
         for line in heap.iter() {
-            wtr.write_record(&line.record)?;
+            wtr.write_record(&headers, &line.record)?;
         }
         wtr.flush()?;
 
@@ -195,6 +611,146 @@ fn main() -> Result<(), Error> {
     }
 }
 
+/// Runs the weighted reservoir sampling algorithm (plain A-Res while
+/// filling, A-ExpJ exponential jumps once full) over `records`, honoring
+/// `--include`/`--exclude` by id. `--include` rows always get an
+/// `f64::INFINITY` key so they're never evicted; `--exclude` rows are
+/// dropped before they're ever keyed. Pulled out of `process_data` so it
+/// can run against in-memory records in tests, independent of the reader
+/// thread and CLI args.
+fn sample_reservoir(
+    records: impl Iterator<Item = Result<StringRecord, Error>>,
+    sample_count: usize,
+    weight_col: Option<usize>,
+    id_col: usize,
+    include: &[String],
+    exclude: &[String],
+    rng: &mut StdRng,
+) -> Result<BinaryHeap<Line>, Error> {
+    let mut heap = BinaryHeap::new();
+    let mut i = 0;
+    // A-ExpJ state: once the reservoir is full we stop drawing a key for
+    // every record and instead jump ahead by accumulated weight until we
+    // cross `skip_target`, only then computing a replacement key.
+    let mut skip_accum = 0.0_f64;
+    let mut skip_target = 0.0_f64;
+    let mut skip_primed = false;
+
+    for record in records {
+        let record = record?;
+        let id = String::from(record.get(id_col).unwrap());
+        if exclude.contains(&id) {
+            continue;
+        }
+
+        if include.contains(&id) {
+            let weight = get_weight(weight_col, &record);
+            let line = Line {
+                record,
+                weight,
+                randomness: f64::NAN,
+                position_index: f64::INFINITY,
+                tie_breaker: i,
+            };
+            log::trace!("pushing line {:?}", &line);
+            heap.push(line);
+            i += 1;
+            if heap.len() > sample_count {
+                let smallest = heap.pop();
+                if let Some(poor_soul) = smallest {
+                    log::trace!("removing line  {:?}", poor_soul)
+                }
+                // The threshold we may have been jumping towards is gone.
+                skip_primed = false;
+            }
+            continue;
+        }
+
+        let weight: f64 = get_weight(weight_col, &record);
+        if !weight.is_finite() || weight <= 0.0 {
+            panic!("Non-zero weights required for numerical precision.");
+        }
+
+        if heap.len() < sample_count {
+            // Still filling the reservoir: plain A-Res key for every row.
+            let u = rng.gen::<f64>();
+            let log_key = (1.0 / weight) * u.log2();
+            if log_key == 0.0 {
+                panic!("Non-zero weights required for numerical precision.");
+            }
+            let line = Line {
+                record,
+                weight,
+                randomness: u,
+                position_index: log_key,
+                tie_breaker: i,
+            };
+            log::trace!("pushing line {:?}", &line);
+            heap.push(line);
+            i += 1;
+            continue;
+        }
+
+        // Reservoir is full: jump ahead instead of keying every record.
+        // An empty heap here means `sample_count` is 0 - there's no
+        // capacity at all, so every row is dropped without a key.
+        let top_position_index = match heap.peek() {
+            Some(top) => top.position_index,
+            None => continue,
+        };
+        if !top_position_index.is_finite() {
+            // The reservoir is entirely `--include` rows (infinite keys
+            // that must never be evicted), so there's no finite
+            // threshold to jump towards and no room for this row.
+            log::trace!(
+                "dropping record {:?}: reservoir full of --include rows",
+                &record
+            );
+            continue;
+        }
+
+        if !skip_primed {
+            let t_log2 = top_position_index;
+            if t_log2 == 0.0 {
+                panic!("Non-zero weights required for numerical precision.");
+            }
+            let r = rng.gen::<f64>();
+            skip_target = r.ln() / (t_log2 * std::f64::consts::LN_2);
+            skip_accum = 0.0;
+            skip_primed = true;
+        }
+
+        skip_accum += weight;
+        if skip_accum < skip_target {
+            log::trace!("skipping record {:?} without keying it", &record);
+            continue;
+        }
+
+        let t_log2 = heap.peek().expect("reservoir is non-empty").position_index;
+        let t_linear = 2f64.powf(weight * t_log2);
+        let u2 = t_linear + rng.gen::<f64>() * (1.0 - t_linear);
+        let log_key = (1.0 / weight) * u2.log2();
+
+        let line = Line {
+            record,
+            weight,
+            randomness: u2,
+            position_index: log_key,
+            tie_breaker: i,
+        };
+        i += 1;
+        let evicted = heap.pop();
+        if let Some(poor_soul) = evicted {
+            log::trace!("removing line  {:?}", poor_soul)
+        }
+        log::trace!("pushing line {:?}", &line);
+        heap.push(line);
+        skip_primed = false;
+    }
+
+    Ok(heap)
+}
+
 fn get_weight(column: Option<usize>, record: &StringRecord) -> f64 {
     match column {
         Some(i) => record
@@ -204,3 +760,135 @@ fn get_weight(column: Option<usize>, record: &StringRecord) -> f64 {
         None => 1.0,
     }
 }
+
+/// Stringifies a JSON value the way a delimited-text field would hold it:
+/// strings pass through unquoted, `null` becomes empty, everything else
+/// (numbers, bools, nested arrays/objects) uses its JSON representation.
+fn stringify_json(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Maps a parsed JSON object onto `headers`, producing a `StringRecord`
+/// positioned the same way a delimited row would be so the rest of the
+/// pipeline (weight/id/include/exclude lookups) doesn't need to know the
+/// input was JSON Lines. Keys missing from a given object read as empty.
+fn json_to_record(headers: &StringRecord, value: &Value) -> StringRecord {
+    let object = value.as_object();
+    headers
+        .iter()
+        .map(|key| {
+            object
+                .and_then(|o| o.get(key))
+                .map(stringify_json)
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Recovers the JSON type `stringify_json` flattened away: a bare `true` or
+/// `false` becomes a bool, a bare numeric literal becomes a number, and
+/// everything else stays a string. An empty field becomes `null`, mirroring
+/// how `stringify_json` writes `null` as "" - so `null` and an actual empty
+/// string remain indistinguishable after a round trip, same as before.
+fn unstringify_json(field: &str) -> Value {
+    match field {
+        "" => Value::Null,
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => field
+            .parse::<Number>()
+            .map(Value::Number)
+            .unwrap_or_else(|_| Value::String(field.to_string())),
+    }
+}
+
+/// Reconstructs one JSON object per surviving line, using the header names
+/// as keys. Fields that look like a JSON number, bool, or empty/`null` are
+/// parsed back to that type via `unstringify_json`; everything else comes
+/// back out as a JSON string.
+fn record_to_json(headers: &StringRecord, record: &StringRecord) -> Value {
+    let mut map = Map::new();
+    for (key, field) in headers.iter().zip(record.iter()) {
+        map.insert(key.to_string(), unstringify_json(field));
+    }
+    Value::Object(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str, weight: &str) -> Result<StringRecord, Error> {
+        Ok(StringRecord::from(vec![id, weight]))
+    }
+
+    fn sampled_ids(records: &[(String, String)], sample_count: usize, seed: u64) -> Vec<String> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let recs = records.iter().map(|(id, w)| record(id, w));
+        let heap = sample_reservoir(recs, sample_count, Some(1), 0, &[], &[], &mut rng)
+            .expect("sampling should not error");
+        let mut ids: Vec<String> = heap.iter().map(|line| line.record[0].to_string()).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn seeded_sampling_is_reproducible() {
+        let records: Vec<_> = (0..50)
+            .map(|i| (format!("id{i}"), ((i % 7) + 1).to_string()))
+            .collect();
+
+        assert_eq!(sampled_ids(&records, 5, 42), sampled_ids(&records, 5, 42));
+    }
+
+    #[test]
+    fn a_expj_jump_sampling_is_reproducible_and_bounded() {
+        // Enough records that the reservoir fills and the A-ExpJ
+        // exponential-jump path (rather than plain per-element A-Res) does
+        // most of the work.
+        let records: Vec<_> = (0..500)
+            .map(|i| (format!("id{i}"), ((i % 11) + 1).to_string()))
+            .collect();
+
+        let first = sampled_ids(&records, 10, 7);
+        assert_eq!(first.len(), 10);
+        assert_eq!(first, sampled_ids(&records, 10, 7));
+    }
+
+    #[test]
+    fn include_rows_survive_a_reservoir_full_of_only_includes() {
+        // Regression test: the A-ExpJ jump used to compute a non-finite
+        // threshold once the reservoir held nothing but `--include` rows,
+        // which dropped the include row instead of keeping it.
+        let records = [
+            record("X", "1"),
+            record("A", "2"),
+            record("B", "3"),
+            record("C", "4"),
+        ];
+        let mut rng = StdRng::seed_from_u64(1);
+        let include = ["X".to_string()];
+        let heap = sample_reservoir(records.into_iter(), 1, Some(1), 0, &include, &[], &mut rng)
+            .expect("sampling should not error");
+
+        assert_eq!(heap.len(), 1);
+        assert_eq!(heap.peek().unwrap().record[0].to_string(), "X");
+    }
+
+    #[test]
+    fn sample_count_zero_does_not_panic() {
+        // Regression test: the A-ExpJ jump used to `heap.peek().expect(..)`
+        // on an empty heap once the fill guard `heap.len() < sample_count`
+        // was `0 < 0`, panicking instead of yielding an empty reservoir.
+        let records = [record("A", "1"), record("B", "2"), record("C", "3")];
+        let mut rng = StdRng::seed_from_u64(1);
+        let heap = sample_reservoir(records.into_iter(), 0, Some(1), 0, &[], &[], &mut rng)
+            .expect("sampling should not error");
+
+        assert_eq!(heap.len(), 0);
+    }
+}